@@ -1,10 +1,14 @@
-use anyhow::{Context, Result};
-use flate2::{write::GzEncoder, Compression};
-use std::path::PathBuf;
+use anyhow::{bail, Context, Result};
+use flate2::{
+    write::{DeflateEncoder, GzEncoder},
+    Compression,
+};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::{collections::HashMap, io::Write};
-use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RequestMethod {
     GET,
     POST,
@@ -13,24 +17,92 @@ pub enum RequestMethod {
     DELETE,
 }
 
+// case-insensitive, multi-value header storage. Keys are matched on their
+// lowercased form but the casing the entry was created with (first append, or
+// latest insert) is kept for writing responses; repeated headers (e.g. multiple `Accept-Encoding` lines)
+// are kept in arrival order rather than overwriting one another.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderMap {
+    entries: HashMap<String, (String, Vec<String>)>,
+}
+
+impl HeaderMap {
+    pub fn new() -> Self {
+        HeaderMap {
+            entries: HashMap::new(),
+        }
+    }
+
+    // overwrites any existing values for `key`, and the stored casing with it
+    pub fn insert(&mut self, key: &str, val: &str) {
+        self.entries
+            .insert(key.to_ascii_lowercase(), (key.to_string(), vec![val.to_string()]));
+    }
+
+    // adds `val` alongside any existing values for `key`, keeping the casing
+    // the existing entry already has
+    pub fn append(&mut self, key: &str, val: &str) {
+        self.entries
+            .entry(key.to_ascii_lowercase())
+            .and_modify(|(_, values)| values.push(val.to_string()))
+            .or_insert_with(|| (key.to_string(), vec![val.to_string()]));
+    }
+
+    pub fn get_first(&self, key: &str) -> Option<&str> {
+        self.entries
+            .get(&key.to_ascii_lowercase())
+            .and_then(|(_, values)| values.first())
+            .map(String::as_str)
+    }
+
+    pub fn get_all(&self, key: &str) -> &[String] {
+        self.entries
+            .get(&key.to_ascii_lowercase())
+            .map(|(_, values)| values.as_slice())
+            .unwrap_or(&[])
+    }
+
+    // `(original-case key, value)` pairs, one per value, for writing headers out
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .values()
+            .flat_map(|(name, values)| values.iter().map(move |val| (name.as_str(), val.as_str())))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct HttpRequest {
     pub method: RequestMethod,
     pub path: String,
     pub version: f32,
-    pub headers: HashMap<String, String>,
+    pub headers: HeaderMap,
     pub body: Option<Vec<u8>>,
 }
 
 impl HttpRequest {
     // pub fn from_reader(buf: &mut BufReader<&TcpStream>) -> Result<Self, anyhow::Error> {
-    pub async fn from_reader<R: AsyncBufRead + Unpin>(buf: &mut R) -> Result<Self, anyhow::Error> {
+    // `writer` is where the `100 Continue` interim response is flushed to,
+    // when the client asks for it, before the (potentially large) body is read
+    pub async fn from_reader<R, W>(buf: &mut R, writer: &mut W) -> Result<Self, anyhow::Error>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
         let mut req_start_line = String::new();
-        buf.read_line(&mut req_start_line)
+        let bytes_read = buf
+            .read_line(&mut req_start_line)
             .await
             .context("Failed to read HTTP Request start line")?;
 
+        if bytes_read == 0 {
+            bail!("Connection closed by peer");
+        }
+
         let req_parts = req_start_line.split_whitespace().collect::<Vec<_>>();
+        if req_parts.len() < 3 {
+            bail!("Malformed HTTP Request start line");
+        }
+
         let method = parse_request_method(req_parts[0])
             .await
             .context("Failed to parse method from HTTP Request")?;
@@ -44,10 +116,23 @@ impl HttpRequest {
             .await
             .context("Failed to parse req headers")?;
 
+        if let Some(expect) = headers.get_first("Expect") {
+            if expect.eq_ignore_ascii_case("100-continue") {
+                writer
+                    .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+                    .await
+                    .context("Failed to write 100 Continue interim response")?;
+                writer
+                    .flush()
+                    .await
+                    .context("Failed to flush 100 Continue interim response")?;
+            }
+        }
+
         let body = match method {
-            RequestMethod::POST => {
+            RequestMethod::POST | RequestMethod::PUT | RequestMethod::PATCH => {
                 let mut body = Vec::new();
-                if let Some(content_length) = headers.get("Content-Length") {
+                if let Some(content_length) = headers.get_first("Content-Length") {
                     let content_length: usize = content_length
                         .parse()
                         .context("Failed to parse Content-Length header")?;
@@ -61,9 +146,7 @@ impl HttpRequest {
                 Some(body)
             }
 
-            RequestMethod::GET => None, // no req. body for `GET`
-
-            _ => todo!(), // still need to implement `DELETE` and `PUT/PATCH` methods
+            RequestMethod::GET | RequestMethod::DELETE => None, // no req. body expected
         };
 
         let req = HttpRequest {
@@ -76,13 +159,22 @@ impl HttpRequest {
 
         Ok(req)
     }
+
+    // whether the connection should stay open after this request's response is sent
+    pub fn keep_alive(&self) -> bool {
+        match self.headers.get_first("Connection") {
+            Some(value) if value.eq_ignore_ascii_case("close") => false,
+            Some(value) if value.eq_ignore_ascii_case("keep-alive") => true,
+            _ => self.version >= 1.1,
+        }
+    }
 }
 
 pub async fn get_headers<R: AsyncBufRead + Unpin>(
     // buf: &mut BufReader<&TcpStream>,
     buf: &mut R,
-) -> Result<HashMap<String, String>, anyhow::Error> {
-    let mut headers = HashMap::new();
+) -> Result<HeaderMap, anyhow::Error> {
+    let mut headers = HeaderMap::new();
     loop {
         let mut header = String::new();
         buf.read_line(&mut header)
@@ -94,7 +186,7 @@ pub async fn get_headers<R: AsyncBufRead + Unpin>(
 
         if let Some((key, val)) = header.trim().split_once(": ") {
             let val = val.trim_end_matches("\r\n");
-            headers.insert(key.to_string(), val.to_string());
+            headers.append(key, val);
         }
     }
 
@@ -112,12 +204,128 @@ pub async fn parse_request_method(method: &str) -> Result<RequestMethod, anyhow:
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentEncoding {
+    Br,
+    Gzip,
+    Deflate,
+    #[default]
+    Identity,
+}
+
+impl ContentEncoding {
+    // `None` for `Identity` -- no `Content-Encoding` header should be written
+    pub fn as_header_value(&self) -> Option<&'static str> {
+        match self {
+            ContentEncoding::Br => Some("br"),
+            ContentEncoding::Gzip => Some("gzip"),
+            ContentEncoding::Deflate => Some("deflate"),
+            ContentEncoding::Identity => None,
+        }
+    }
+}
+
+// server-supported codings, in preference order (used to break `q`-value ties)
+const SUPPORTED_ENCODINGS: [ContentEncoding; 3] = [
+    ContentEncoding::Br,
+    ContentEncoding::Gzip,
+    ContentEncoding::Deflate,
+];
+
+// parses an `Accept-Encoding` header (`token;q=value, token;q=value, ...`) and
+// picks the best supported coding, honoring `*` as a wildcard and excluding any
+// coding explicitly set to `q=0` even when a wildcard would otherwise match it
+pub fn negotiate_encoding(accept_encoding: &str) -> ContentEncoding {
+    let mut explicit: HashMap<&str, f32> = HashMap::new();
+    let mut forbidden: std::collections::HashSet<&str> = std::collections::HashSet::new();
+    let mut wildcard_q: Option<f32> = None;
+
+    for token in accept_encoding.split(',') {
+        let token = token.trim();
+        if token.is_empty() {
+            continue;
+        }
+
+        let mut parts = token.split(';');
+        let coding = parts.next().unwrap_or("").trim();
+
+        let mut q = 1.0f32;
+        for param in parts {
+            if let Some(val) = param.trim().strip_prefix("q=") {
+                q = val.trim().parse().unwrap_or(1.0);
+            }
+        }
+
+        if q <= 0.0 {
+            // a `q=0` coding is an explicit exclusion, not just a low preference --
+            // it must override the wildcard rather than being dropped silently
+            if coding != "*" {
+                forbidden.insert(coding);
+            }
+            continue;
+        }
+
+        if coding == "*" {
+            wildcard_q = Some(q);
+        } else {
+            explicit.insert(coding, q);
+        }
+    }
+
+    let mut best: Option<(ContentEncoding, f32)> = None;
+    for encoding in SUPPORTED_ENCODINGS {
+        let name = encoding.as_header_value().unwrap();
+        if forbidden.contains(name) {
+            continue;
+        }
+        let q = explicit.get(name).copied().or(wildcard_q);
+
+        if let Some(q) = q {
+            let is_better = match best {
+                Some((_, best_q)) => q > best_q,
+                None => true,
+            };
+            if is_better {
+                best = Some((encoding, q));
+            }
+        }
+    }
+
+    best.map(|(encoding, _)| encoding)
+        .unwrap_or(ContentEncoding::Identity)
+}
+
+fn encode_body(body: &[u8], encoding: ContentEncoding) -> Result<Vec<u8>, anyhow::Error> {
+    match encoding {
+        ContentEncoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(body)?;
+            Ok(encoder.finish()?)
+        }
+        ContentEncoding::Deflate => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(body)?;
+            Ok(encoder.finish()?)
+        }
+        ContentEncoding::Br => {
+            let mut out = Vec::new();
+            let mut writer = brotli::CompressorWriter::new(&mut out, 4096, 11, 22);
+            writer.write_all(body)?;
+            writer.flush()?;
+            drop(writer);
+            Ok(out)
+        }
+        ContentEncoding::Identity => Ok(body.to_vec()),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct HttpResponse {
     pub status_code: u16,
     pub status_text: String,
-    pub headers: HashMap<String, String>,
+    pub headers: HeaderMap,
     pub body: Option<Vec<u8>>,
+    pub content_encoding: ContentEncoding,
 }
 
 impl HttpResponse {
@@ -125,8 +333,9 @@ impl HttpResponse {
         HttpResponse {
             status_code: 200,
             status_text: "OK".to_string(),
-            headers: HashMap::new(),
+            headers: HeaderMap::new(),
             body: None,
+            content_encoding: ContentEncoding::Identity,
         }
     }
 
@@ -135,20 +344,28 @@ impl HttpResponse {
         match code {
             200 => self.status_text = "OK".to_string(),
             201 => self.status_text = "Created".to_string(),
-            404 => self.status_text = "Not Found".to_string(),
+            204 => self.status_text = "No Content".to_string(),
+            206 => self.status_text = "Partial Content".to_string(),
+            304 => self.status_text = "Not Modified".to_string(),
             400 => self.status_text = "Bad Request".to_string(),
             401 => self.status_text = "Unauthorized".to_string(),
+            404 => self.status_text = "Not Found".to_string(),
+            405 => self.status_text = "Method Not Allowed".to_string(),
+            416 => self.status_text = "Range Not Satisfiable".to_string(),
             _ => self.status_text = "UNKNOWN STATUS".to_string(),
         }
     }
 
     pub fn set_header(&mut self, key: &str, val: &str) {
-        self.headers.insert(key.to_string(), val.to_string());
+        self.headers.insert(key, val);
+    }
+
+    pub fn set_content_encoding(&mut self, encoding: ContentEncoding) {
+        self.content_encoding = encoding;
     }
 
     pub fn set_content_type(&mut self, content_type: ContentType) {
-        self.headers
-            .insert("Content-Type".to_string(), content_type.to_string()); // simplified via ToString impl
+        self.set_header("Content-Type", &content_type.to_string()); // simplified via ToString impl
     }
 
     pub fn set_body(&mut self, body: Vec<u8>) {
@@ -163,9 +380,50 @@ impl HttpResponse {
         &mut self,
         dir_path: &PathBuf,
         file_path: &str,
+        request_headers: &HeaderMap,
     ) -> Result<(), anyhow::Error> {
-        let path = PathBuf::from(dir_path).join(file_path);
-        let data = tokio::fs::read(path)
+        let path = match resolve_file_path(dir_path, file_path) {
+            Some(path) => path,
+            None => {
+                self.set_status_code(400);
+                return Ok(());
+            }
+        };
+
+        let metadata = match tokio::fs::metadata(&path).await {
+            Ok(metadata) => metadata,
+            Err(_) => {
+                self.set_status_code(404);
+                return Ok(());
+            }
+        };
+
+        let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+        let mtime_secs = modified.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let etag = format!("\"{}-{}\"", metadata.len(), mtime_secs);
+        let last_modified = http_date(modified);
+        let file_size = metadata.len();
+
+        self.set_header("Accept-Ranges", "bytes");
+
+        if is_not_modified(request_headers, &etag, mtime_secs) {
+            self.set_status_code(304);
+            self.set_header("ETag", &etag);
+            self.set_header("Last-Modified", &last_modified);
+            return Ok(());
+        }
+
+        let range = request_headers
+            .get_first("Range")
+            .and_then(|range| parse_range(range, file_size));
+
+        if let Some(Err(())) = range {
+            self.set_status_code(416);
+            self.set_header("Content-Range", &format!("bytes */{}", file_size));
+            return Ok(());
+        }
+
+        let data = tokio::fs::read(&path)
             .await
             // .context("Failed to read data from given file path")?; // instead set response for 404 Not Found
             .map_err(|_err| {
@@ -173,8 +431,31 @@ impl HttpResponse {
             });
 
         if let Ok(data) = data {
-            self.set_body(data);
-            self.set_content_type(ContentType::OctetStream);
+            self.set_content_type(content_type_for_path(&path));
+            self.set_header("ETag", &etag);
+            self.set_header("Last-Modified", &last_modified);
+
+            match range {
+                // `byte_range` was validated against the stat()'d `file_size`, but the file
+                // may have been truncated between that stat and this read -- re-check against
+                // the actual bytes in hand rather than trusting the earlier length
+                Some(Ok(byte_range))
+                    if (byte_range.start as usize) < data.len() =>
+                {
+                    let end = (byte_range.end as usize).min(data.len().saturating_sub(1));
+                    self.set_status_code(206);
+                    self.set_header(
+                        "Content-Range",
+                        &format!("bytes {}-{}/{}", byte_range.start, end, file_size),
+                    );
+                    self.set_body(data[byte_range.start as usize..=end].to_vec());
+                }
+                Some(Ok(_)) => {
+                    self.set_status_code(416);
+                    self.set_header("Content-Range", &format!("bytes */{}", file_size));
+                }
+                _ => self.set_body(data),
+            }
         }
 
         Ok(())
@@ -190,8 +471,8 @@ impl HttpResponse {
         );
 
         // headers
-        for (key, value) in &self.headers {
-            if key == "Content-Length" {
+        for (key, value) in self.headers.iter() {
+            if key.eq_ignore_ascii_case("Content-Length") {
                 continue;
             }
 
@@ -199,35 +480,32 @@ impl HttpResponse {
         }
 
         // check for body content
-        if self.body.is_some() {
+        if self.status_code == 204 {
+            // per spec, `204 No Content` has no body and no `Content-Length`
+            res_buffer.extend_from_slice("\r\n".as_bytes());
+        } else if self.body.is_some() {
             // content type (default)
-            if self.headers.get("Content-Type").is_none() {
+            if self.headers.get_first("Content-Type").is_none() {
                 res_buffer.extend_from_slice("Content-Type: text/plain\r\n".as_bytes());
             }
 
-            match self.headers.get("Content-Encoding") {
-                Some(_) => {
-                    // encoding
-                    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-                    encoder.write_all(self.body.as_ref().unwrap())?;
-                    let enc_buf = encoder.finish()?;
-
-                    res_buffer.extend_from_slice(
-                        format!("Content-Length: {}\r\n", enc_buf.len()).as_bytes(),
-                    );
-                    res_buffer.extend_from_slice("\r\n".as_bytes());
-                    res_buffer.extend_from_slice(&enc_buf);
-                }
-                None => {
-                    res_buffer.extend_from_slice(
-                        format!("Content-Length: {}\r\n", self.body.as_ref().unwrap().len())
-                            .as_bytes(),
-                    );
-
-                    res_buffer.extend_from_slice("\r\n".as_bytes());
-                    res_buffer.extend_from_slice(self.body.as_ref().unwrap());
-                }
+            let body = self.body.as_ref().unwrap();
+            // a 206 body is a byte slice described by `Content-Range`; compressing it would
+            // change its length without updating those offsets, so always serve it as identity
+            let encoding = if self.status_code == 206 {
+                ContentEncoding::Identity
+            } else {
+                self.content_encoding
+            };
+            if let Some(value) = encoding.as_header_value() {
+                res_buffer.extend_from_slice(format!("Content-Encoding: {}\r\n", value).as_bytes());
             }
+
+            let out_body = encode_body(body, encoding)?;
+            res_buffer
+                .extend_from_slice(format!("Content-Length: {}\r\n", out_body.len()).as_bytes());
+            res_buffer.extend_from_slice("\r\n".as_bytes());
+            res_buffer.extend_from_slice(&out_body);
         } else {
             // no body, write EOF / CRLF
             res_buffer.extend_from_slice("Content-Length: 0\r\n\r\n".as_bytes());
@@ -244,9 +522,309 @@ impl Default for HttpResponse {
     }
 }
 
+// -- Router -- //
+pub type RouteParams = HashMap<String, String>;
+
+type BoxFuture<T> = std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send>>;
+
+// a registered route's handler: takes the (owned) request and its captured
+// path params, returns the response to write back
+pub type RouteHandler =
+    Box<dyn Fn(HttpRequest, RouteParams) -> BoxFuture<Result<HttpResponse, anyhow::Error>> + Send + Sync>;
+
+enum Segment {
+    Literal(String),
+    Param(String),
+}
+
+struct Route {
+    method: RequestMethod,
+    segments: Vec<Segment>,
+    handler: RouteHandler,
+}
+
+// resolves `(RequestMethod, path)` pairs to a registered handler, binding
+// `:name` path segments into a `RouteParams` map. Falls back to a
+// configurable 404 handler when no route matches the path at all, or a 405
+// handler when the path matches but not for the request's method.
+pub struct Router {
+    routes: Vec<Route>,
+    not_found: RouteHandler,
+    method_not_allowed: RouteHandler,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Router {
+            routes: Vec::new(),
+            not_found: default_not_found_handler(),
+            method_not_allowed: default_method_not_allowed_handler(),
+        }
+    }
+
+    pub fn set_not_found(&mut self, handler: RouteHandler) {
+        self.not_found = handler;
+    }
+
+    pub fn set_method_not_allowed(&mut self, handler: RouteHandler) {
+        self.method_not_allowed = handler;
+    }
+
+    // `pattern` is a `/`-separated path where segments starting with `:` bind
+    // into the resolved `RouteParams`, e.g. `/echo/:msg` or `/files/:name`
+    pub fn register(&mut self, method: RequestMethod, pattern: &str, handler: RouteHandler) {
+        let segments = pattern
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(name) => Segment::Param(name.to_string()),
+                None => Segment::Literal(segment.to_string()),
+            })
+            .collect();
+
+        self.routes.push(Route {
+            method,
+            segments,
+            handler,
+        });
+    }
+
+    pub async fn dispatch(&self, request: HttpRequest) -> Result<HttpResponse, anyhow::Error> {
+        let path_segments: Vec<&str> = request.path.split('/').filter(|s| !s.is_empty()).collect();
+        let mut path_matched = false;
+
+        for route in &self.routes {
+            if route.segments.len() != path_segments.len() {
+                continue;
+            }
+
+            let mut params = RouteParams::new();
+            let matched = route.segments.iter().zip(path_segments.iter()).all(
+                |(segment, actual)| match segment {
+                    Segment::Literal(literal) => literal == actual,
+                    Segment::Param(name) => {
+                        params.insert(name.clone(), actual.to_string());
+                        true
+                    }
+                },
+            );
+
+            if !matched {
+                continue;
+            }
+
+            path_matched = true;
+
+            if route.method == request.method {
+                return (route.handler)(request, params).await;
+            }
+        }
+
+        if path_matched {
+            (self.method_not_allowed)(request, RouteParams::new()).await
+        } else {
+            (self.not_found)(request, RouteParams::new()).await
+        }
+    }
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn default_not_found_handler() -> RouteHandler {
+    Box::new(|_request, _params| {
+        Box::pin(async {
+            let mut res = HttpResponse::new();
+            res.set_status_code(404);
+            Ok(res)
+        })
+    })
+}
+
+fn default_method_not_allowed_handler() -> RouteHandler {
+    Box::new(|_request, _params| {
+        Box::pin(async {
+            let mut res = HttpResponse::new();
+            res.set_status_code(405);
+            Ok(res)
+        })
+    })
+}
+
+// -- conditional GET (ETag / Last-Modified) -- //
+fn is_not_modified(headers: &HeaderMap, etag: &str, mtime_secs: u64) -> bool {
+    if let Some(if_none_match) = headers.get_first("If-None-Match") {
+        return if_none_match
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == etag || tag == "*");
+    }
+
+    if let Some(if_modified_since) = headers.get_first("If-Modified-Since") {
+        if let Some(since) = parse_http_date(if_modified_since) {
+            return mtime_secs <= since;
+        }
+    }
+
+    false
+}
+
+// -- byte-range requests (`Range: bytes=start-end`) -- //
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64, // inclusive
+}
+
+// parses a `Range` header against a file of `file_size` bytes. Returns `None`
+// when the header isn't a `bytes=` range (so callers should ignore it and
+// serve the full body), `Some(Err(()))` when it's malformed or unsatisfiable
+// (-> 416), or `Some(Ok(range))` for a valid single range (-> 206). Only a
+// single range is supported; `start-`, `-suffixlen`, and `start-end` forms are.
+pub fn parse_range(range_header: &str, file_size: u64) -> Option<Result<ByteRange, ()>> {
+    let spec = range_header.strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return Some(Err(())); // multiple ranges not supported
+    }
+
+    let (start_str, end_str) = match spec.split_once('-') {
+        Some(parts) => parts,
+        None => return Some(Err(())),
+    };
+
+    if start_str.is_empty() {
+        let suffix_len: u64 = match end_str.parse() {
+            Ok(len) => len,
+            Err(_) => return Some(Err(())),
+        };
+        if suffix_len == 0 || file_size == 0 {
+            return Some(Err(()));
+        }
+        let start = file_size.saturating_sub(suffix_len);
+        return Some(Ok(ByteRange {
+            start,
+            end: file_size - 1,
+        }));
+    }
+
+    let start: u64 = match start_str.parse() {
+        Ok(start) => start,
+        Err(_) => return Some(Err(())),
+    };
+    let end: u64 = if end_str.is_empty() {
+        file_size.saturating_sub(1)
+    } else {
+        match end_str.parse() {
+            Ok(end) => end,
+            Err(_) => return Some(Err(())),
+        }
+    };
+
+    if file_size == 0 || start > end || start >= file_size {
+        return Some(Err(()));
+    }
+
+    Some(Ok(ByteRange {
+        start,
+        end: end.min(file_size - 1),
+    }))
+}
+
+// -- RFC 7231 IMF-fixdate helpers -- //
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+// days since the Unix epoch -> (year, month, day), via Howard Hinnant's
+// `civil_from_days` algorithm (proleptic Gregorian calendar)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = (z - era * 146097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = if m <= 2 { y + 1 } else { y };
+    (year, m, d)
+}
+
+// inverse of `civil_from_days`
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = (if month > 2 { month - 3 } else { month + 9 }) as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// formats a `SystemTime` as an RFC 7231 IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+pub fn http_date(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let days = (secs / 86400) as i64;
+    let time_of_day = secs % 86400;
+    let (hour, minute, second) = (
+        time_of_day / 3600,
+        (time_of_day % 3600) / 60,
+        time_of_day % 60,
+    );
+
+    let (year, month, day) = civil_from_days(days);
+    let weekday = ((days.rem_euclid(7) + 4) % 7) as usize; // 1970-01-01 (day 0) was a Thursday
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[weekday],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        hour,
+        minute,
+        second
+    )
+}
+
+// parses an RFC 7231 IMF-fixdate into epoch seconds; lenient about the
+// leading weekday name (not validated), which is all `If-Modified-Since` needs
+pub fn parse_http_date(value: &str) -> Option<u64> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    // `Sun, 06 Nov 1994 08:49:37 GMT` -> weekday, day, month, year, time, `GMT`
+    if parts.len() != 6 {
+        return None;
+    }
+
+    let day: u32 = parts[1].parse().ok()?;
+    let month = MONTHS.iter().position(|m| *m == parts[2])? as u32 + 1;
+    let year: i64 = parts[3].parse().ok()?;
+
+    let mut time_parts = parts[4].split(':');
+    let hour: u64 = time_parts.next()?.parse().ok()?;
+    let minute: u64 = time_parts.next()?.parse().ok()?;
+    let second: u64 = time_parts.next()?.parse().ok()?;
+
+    let days = days_from_civil(year, month, day);
+    Some((days * 86400) as u64 + hour * 3600 + minute * 60 + second)
+}
+
 #[derive(Debug, Clone)]
 pub enum ContentType {
     TextPlain,
+    TextHtml,
+    TextCss,
+    TextJavascript,
+    Json,
+    Png,
+    Jpeg,
+    Svg,
+    Wasm,
     OctetStream,
 }
 
@@ -254,7 +832,89 @@ impl ToString for ContentType {
     fn to_string(&self) -> String {
         match self {
             ContentType::TextPlain => "text/plain".to_string(),
+            ContentType::TextHtml => "text/html".to_string(),
+            ContentType::TextCss => "text/css".to_string(),
+            ContentType::TextJavascript => "text/javascript".to_string(),
+            ContentType::Json => "application/json".to_string(),
+            ContentType::Png => "image/png".to_string(),
+            ContentType::Jpeg => "image/jpeg".to_string(),
+            ContentType::Svg => "image/svg+xml".to_string(),
+            ContentType::Wasm => "application/wasm".to_string(),
             ContentType::OctetStream => "application/octet-stream".to_string(),
         }
     }
 }
+
+// guesses a `ContentType` from a file path's extension, falling back to
+// `application/octet-stream` for anything unrecognized
+fn content_type_for_path(path: &Path) -> ContentType {
+    match path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("html") | Some("htm") => ContentType::TextHtml,
+        Some("css") => ContentType::TextCss,
+        Some("js") => ContentType::TextJavascript,
+        Some("json") => ContentType::Json,
+        Some("png") => ContentType::Png,
+        Some("jpg") | Some("jpeg") => ContentType::Jpeg,
+        Some("svg") => ContentType::Svg,
+        Some("txt") => ContentType::TextPlain,
+        Some("wasm") => ContentType::Wasm,
+        _ => ContentType::OctetStream,
+    }
+}
+
+// decodes `%XX` percent-escapes in a URL path segment into their raw bytes,
+// then validates the result as UTF-8
+fn percent_decode(input: &str) -> Result<String, anyhow::Error> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 3 <= bytes.len() {
+            if let Ok(hex) = std::str::from_utf8(&bytes[i + 1..i + 3]) {
+                if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                    decoded.push(byte);
+                    i += 3;
+                    continue;
+                }
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8(decoded).context("Invalid UTF-8 in percent-decoded path")
+}
+
+// rejects `..` components and absolute paths so a decoded path can't escape
+// the served directory (e.g. `/files/%2Fetc%2Fpasswd` decoding to `/etc/passwd`,
+// which `PathBuf::join` would otherwise treat as replacing `dir_path` entirely)
+fn has_parent_traversal(path: &str) -> bool {
+    std::path::Path::new(path).components().any(|component| {
+        matches!(
+            component,
+            std::path::Component::ParentDir
+                | std::path::Component::RootDir
+                | std::path::Component::Prefix(_)
+        )
+    })
+}
+
+// shared by every `/files` handler (reads and writes alike): percent-decodes
+// the route's `:name` param and rejects anything that would escape `dir_path`,
+// returning the resolved on-disk path only when both checks pass
+pub fn resolve_file_path(dir_path: &Path, file_path: &str) -> Option<PathBuf> {
+    let decoded_path = percent_decode(file_path).ok()?;
+
+    if has_parent_traversal(&decoded_path) {
+        return None;
+    }
+
+    Some(dir_path.join(&decoded_path))
+}
@@ -1,130 +1,258 @@
 use anyhow::{bail, Context, Result};
-use http_server_starter_rust::http::{HttpRequest, HttpResponse, RequestMethod};
+use http_server_starter_rust::http::{
+    negotiate_encoding, resolve_file_path, HeaderMap, HttpRequest, HttpResponse, RequestMethod,
+    Router,
+};
 use std::env;
 use std::path::PathBuf;
+use std::time::Duration;
 use tokio::io::{AsyncWriteExt, BufReader};
 use tokio::net::{TcpListener, TcpStream};
 
-async fn handler(mut stream: TcpStream, dir_path: PathBuf) -> Result<(), anyhow::Error> {
-    // -- init reader + read request -- //
-    let mut buf = BufReader::new(&mut stream);
-    let request = HttpRequest::from_reader(&mut buf).await?;
-    let path = request.path.as_str();
-    let mut _res_buffer = Vec::new();
+// how long a keep-alive connection may sit idle before the task gives up on it
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
 
-    // access headers -- specifically for `/user-agent`
-    let headers = request.headers;
-    let mut user_agent = String::new();
-    let mut encoding = String::new();
+async fn handler(stream: TcpStream, dir_path: PathBuf) -> Result<(), anyhow::Error> {
+    // -- init reader/writer halves, owned independently so we can read + write across loop iterations -- //
+    let (read_half, mut write_half) = stream.into_split();
+    let mut buf = BufReader::new(read_half);
+    let router = build_router(dir_path);
 
-    if let Some(ua) = headers.get("User-Agent") {
-        user_agent = ua.to_string();
-    }
+    loop {
+        let request = match tokio::time::timeout(
+            IDLE_TIMEOUT,
+            HttpRequest::from_reader(&mut buf, &mut write_half),
+        )
+        .await
+        {
+            Ok(Ok(request)) => request,
+            Ok(Err(_)) => break, // EOF or malformed request -- close the connection
+            Err(_) => break,     // idle timeout -- half-open client, close the connection
+        };
+
+        let keep_alive = request.keep_alive();
+
+        let mut res = router
+            .dispatch(request)
+            .await
+            .context("Failed to dispatch request to router")?;
 
-    if let Some(enc) = headers.get("Accept-Encoding") {
-        let encodings = enc.split(',').map(|e| e.trim()).collect::<Vec<&str>>();
+        res.set_header(
+            "Connection",
+            if keep_alive { "keep-alive" } else { "close" },
+        );
+        let res_buffer = res
+            .write_to_buffer()
+            .context("Failed to write HTTP response to buffer")?;
 
-        if encodings.contains(&"gzip") {
-            encoding = "gzip".to_string();
+        write_half
+            .write_all(&res_buffer)
+            .await
+            .context("Failed to write response to TCP stream")?;
+        write_half
+            .flush()
+            .await
+            .context("Failed to flush TCP stream")?;
+
+        if !keep_alive {
+            break;
         }
     }
 
-    let method = request.method;
+    Ok(())
+}
 
-    // 'routing'
-    match path {
-        "/" => {
-            let res = HttpResponse::new();
-            _res_buffer = res
-                .write_to_buffer()
-                .context("Failed to write HTTP response from `/` path to buffer")?;
-        }
+// registers the server's endpoints -- adding a route is just another `register` call
+fn build_router(dir_path: PathBuf) -> Router {
+    let mut router = Router::new();
 
-        path if path.starts_with("/echo/") => {
-            let mut res = echo_route(path);
-            if !encoding.is_empty() {
-                res.headers.insert("Content-Encoding".to_string(), encoding);
-            }
-            _res_buffer = res
-                .write_to_buffer()
-                .context("Failed to write HTTP response from `/echo/` endpoint to buffer")?;
-        }
+    router.register(
+        RequestMethod::GET,
+        "/",
+        Box::new(|_request, _params| Box::pin(async { Ok(HttpResponse::new()) })),
+    );
 
-        path if path.starts_with("/files/") => match method {
-            RequestMethod::GET => {
-                let file_path = &path["/files/".len()..];
-                let res = file_route(dir_path, file_path).await.context(
-                    "Failed to write HTTP response from given directory at `/files/` endpoint",
-                )?;
-                let err_msg = format!(
-                    "Failed to write HTTP response from `/files/{:?} endpoint",
-                    file_path
-                );
-
-                _res_buffer = res.write_to_buffer().context(err_msg)?;
-            }
+    router.register(
+        RequestMethod::GET,
+        "/echo/:msg",
+        Box::new(|request, params| {
+            Box::pin(async move {
+                let msg = params.get("msg").map(String::as_str).unwrap_or("");
+                let mut res = echo_route(msg);
+                apply_negotiated_encoding(&mut res, &request.headers);
+                Ok(res)
+            })
+        }),
+    );
+
+    router.register(
+        RequestMethod::GET,
+        "/user-agent",
+        Box::new(|request, _params| {
+            Box::pin(async move {
+                let user_agent = request.headers.get_first("User-Agent").unwrap_or("").to_string();
+                let mut res = user_agent_route(user_agent);
+                apply_negotiated_encoding(&mut res, &request.headers);
+                Ok(res)
+            })
+        }),
+    );
 
-            RequestMethod::POST => {
-                let file_path = &path["/files/".len()..];
-                let full_path = dir_path.join(file_path);
+    let get_dir_path = dir_path.clone();
+    router.register(
+        RequestMethod::GET,
+        "/files/:name",
+        Box::new(move |request, params| {
+            let dir_path = get_dir_path.clone();
+            Box::pin(async move {
+                let file_path = params.get("name").map(String::as_str).unwrap_or("");
+                let mut res = file_route(dir_path, file_path, &request.headers)
+                    .await
+                    .context(
+                        "Failed to write HTTP response from given directory at `/files/` endpoint",
+                    )?;
+                apply_negotiated_encoding(&mut res, &request.headers);
+                Ok(res)
+            })
+        }),
+    );
 
-                if let Some(body) = request.body {
+    let post_dir_path = dir_path.clone();
+    router.register(
+        RequestMethod::POST,
+        "/files/:name",
+        Box::new(move |request, params| {
+            let dir_path = post_dir_path.clone();
+            Box::pin(async move {
+                let file_path = params.get("name").map(String::as_str).unwrap_or("");
+                let mut res = HttpResponse::new();
+                let full_path = match resolve_file_path(&dir_path, file_path) {
+                    Some(path) => path,
+                    None => {
+                        res.set_status_code(400);
+                        return Ok(res);
+                    }
+                };
+
+                if let Some(body) = &request.body {
                     tokio::fs::File::create(full_path)
                         .await?
-                        .write_all(&body)
+                        .write_all(body)
                         .await
                         .context("Failed to write uploaded file")?;
                 }
 
-                let mut res = HttpResponse::new();
                 res.set_status_code(201);
-                _res_buffer = res.write_to_buffer()?;
-            }
+                Ok(res)
+            })
+        }),
+    );
 
-            _ => {
-                // todo(?): implement 'DELETE' + 'PUT/PATCH' methods
+    let put_dir_path = dir_path.clone();
+    router.register(
+        RequestMethod::PUT,
+        "/files/:name",
+        Box::new(move |request, params| {
+            let dir_path = put_dir_path.clone();
+            Box::pin(async move {
+                let file_path = params.get("name").map(String::as_str).unwrap_or("");
                 let mut res = HttpResponse::new();
-                res.set_status_code(401);
-                _res_buffer = res
-                    .write_to_buffer()
-                    .context("Failed to write HTTP response for unknown route endpoint")?;
-            }
-        },
+                let full_path = match resolve_file_path(&dir_path, file_path) {
+                    Some(path) => path,
+                    None => {
+                        res.set_status_code(400);
+                        return Ok(res);
+                    }
+                };
+                let existed = tokio::fs::metadata(&full_path).await.is_ok();
 
-        "/user-agent" => {
-            let res = user_agent_route(user_agent);
-            _res_buffer = res
-                .write_to_buffer()
-                .context("Failed to write HTTP response from `/user_agent` endpoint to buffer")?;
-        }
+                tokio::fs::write(&full_path, request.body.as_deref().unwrap_or(&[]))
+                    .await
+                    .context("Failed to write file for PUT request")?;
 
-        _ => {
-            let mut res = HttpResponse::new();
-            res.set_status_code(404);
-            _res_buffer = res
-                .write_to_buffer()
-                .context("Failed to write HTTP response for unknown route endpoint")?;
-        }
-    }
+                res.set_status_code(if existed { 200 } else { 201 });
+                Ok(res)
+            })
+        }),
+    );
 
-    // write response buffer to stream
-    stream
-        .write_all(&_res_buffer)
-        .await
-        .context("Failed to write response to TCP stream")?;
-    stream
-        .flush()
-        .await
-        .context("Failed to flush TPCP stream")?;
+    let patch_dir_path = dir_path.clone();
+    router.register(
+        RequestMethod::PATCH,
+        "/files/:name",
+        Box::new(move |request, params| {
+            let dir_path = patch_dir_path.clone();
+            Box::pin(async move {
+                let file_path = params.get("name").map(String::as_str).unwrap_or("");
+                let mut res = HttpResponse::new();
+                let full_path = match resolve_file_path(&dir_path, file_path) {
+                    Some(path) => path,
+                    None => {
+                        res.set_status_code(400);
+                        return Ok(res);
+                    }
+                };
 
-    Ok(())
+                if tokio::fs::metadata(&full_path).await.is_err() {
+                    res.set_status_code(404);
+                    return Ok(res);
+                }
+
+                if let Some(body) = &request.body {
+                    tokio::fs::OpenOptions::new()
+                        .append(true)
+                        .open(&full_path)
+                        .await
+                        .context("Failed to open file for PATCH request")?
+                        .write_all(body)
+                        .await
+                        .context("Failed to append to file")?;
+                }
+
+                res.set_status_code(200);
+                Ok(res)
+            })
+        }),
+    );
+
+    router.register(
+        RequestMethod::DELETE,
+        "/files/:name",
+        Box::new(move |_request, params| {
+            let dir_path = dir_path.clone();
+            Box::pin(async move {
+                let file_path = params.get("name").map(String::as_str).unwrap_or("");
+                let mut res = HttpResponse::new();
+                let full_path = match resolve_file_path(&dir_path, file_path) {
+                    Some(path) => path,
+                    None => {
+                        res.set_status_code(400);
+                        return Ok(res);
+                    }
+                };
+
+                match tokio::fs::remove_file(&full_path).await {
+                    Ok(_) => res.set_status_code(204),
+                    Err(_) => res.set_status_code(404),
+                }
+                Ok(res)
+            })
+        }),
+    );
+
+    router
+}
+
+fn apply_negotiated_encoding(res: &mut HttpResponse, request_headers: &HeaderMap) {
+    if let Some(enc) = request_headers.get_first("Accept-Encoding") {
+        res.set_content_encoding(negotiate_encoding(enc));
+    }
 }
 
 // -- HELPERS re: path / endpoints -- //
-pub fn echo_route(path: &str) -> HttpResponse {
+pub fn echo_route(msg: &str) -> HttpResponse {
     let mut res = HttpResponse::new();
-    let body = path.replace("/echo/", "").as_bytes().to_vec();
-    res.set_body(body);
+    res.set_body(msg.as_bytes().to_vec());
 
     res
 }
@@ -136,9 +264,13 @@ pub fn user_agent_route(user_agent: String) -> HttpResponse {
     res
 }
 
-pub async fn file_route(dir_path: PathBuf, file_path: &str) -> Result<HttpResponse, anyhow::Error> {
+pub async fn file_route(
+    dir_path: PathBuf,
+    file_path: &str,
+    request_headers: &HeaderMap,
+) -> Result<HttpResponse, anyhow::Error> {
     let mut res = HttpResponse::new();
-    res.set_file_content(&dir_path, file_path)
+    res.set_file_content(&dir_path, file_path, request_headers)
         .await
         .context("Failed to set file contents to Response")?;
 